@@ -0,0 +1,322 @@
+//! Encoding detection and byte decoding.
+//!
+//! [`reader`](crate::reader) tokenizes the *raw* input: it scans for `<`,
+//! `>`, `&` and `"` directly in the byte stream before anything is decoded,
+//! because decoding the whole document up front would defeat incremental
+//! parsing. That scan is safe for every encoding `encoding_rs` knows about
+//! except the escape-based Japanese encodings (`ISO-2022-JP` and its
+//! variants), which reuse ASCII-range bytes as the second half of an
+//! `ESC`-introduced two-byte designation. [`Decoder`] tracks the shift state
+//! those designations put the stream into, so the tokenizer can suppress
+//! structural matching while it is anything other than plain ASCII.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+
+use encoding_rs::{
+    DecoderResult, Encoding, BIG5, EUC_JP, EUC_KR, GB18030, ISO_2022_JP, ISO_8859_2, KOI8_R,
+    SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251, WINDOWS_1252,
+};
+
+/// BOM for UTF-8 documents.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// BOM for big-endian UTF-16 documents.
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+/// BOM for little-endian UTF-16 documents.
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+
+/// Detects the encoding of `bytes` from a leading byte-order mark, or, for
+/// UTF-16 without one, from the byte pattern of a leading `<?`.
+///
+/// Returns the detected encoding together with the length of the BOM that
+/// should be skipped before parsing continues (`0` when there is none).
+/// Falls back to `(UTF_8, 0)` when neither is present.
+///
+/// This only ever reports a deterministic result; it deliberately never
+/// falls through to a heuristic guess, so every existing caller keeps its
+/// current deterministic behavior. [`sniff_encoding`] is the separate,
+/// opt-in heuristic pass for documents with neither a BOM nor a declared
+/// `encoding=` attribute, distinguished from this function's result by
+/// [`SniffedEncoding`] rather than by widening this one's return type.
+pub fn detect_encoding(bytes: &[u8]) -> Option<(&'static Encoding, usize)> {
+    if bytes.starts_with(UTF8_BOM) {
+        Some((UTF_8, UTF8_BOM.len()))
+    } else if bytes.starts_with(UTF16BE_BOM) {
+        Some((UTF_16BE, UTF16BE_BOM.len()))
+    } else if bytes.starts_with(UTF16LE_BOM) {
+        Some((UTF_16LE, UTF16LE_BOM.len()))
+    } else if bytes.starts_with(b"\x00<\x00?") {
+        Some((UTF_16BE, 0))
+    } else if bytes.starts_with(b"<\x00?\x00") {
+        Some((UTF_16LE, 0))
+    } else {
+        Some((UTF_8, 0))
+    }
+}
+
+/// How confident [`sniff_encoding`] is about its guess for a document with
+/// neither a BOM nor a declared `encoding=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedEncoding {
+    /// The leading window parsed as valid UTF-8 and contained at least one
+    /// multi-byte sequence, which is strong evidence the document is UTF-8.
+    Confident(&'static Encoding),
+    /// No BOM and no valid multi-byte UTF-8 evidence; this is simply the
+    /// candidate from [`SNIFF_CANDIDATES`] that decoded the leading window
+    /// with the fewest replacement characters.
+    Guessed(&'static Encoding),
+}
+
+impl SniffedEncoding {
+    /// The encoding this result recommends, regardless of confidence.
+    pub fn encoding(&self) -> &'static Encoding {
+        match *self {
+            SniffedEncoding::Confident(e) | SniffedEncoding::Guessed(e) => e,
+        }
+    }
+}
+
+/// Legacy encodings [`sniff_encoding`] scores when there is no valid
+/// multi-byte UTF-8 evidence. Not exhaustive: covers the major CJK encodings
+/// plus the common single-byte Western/Cyrillic ones.
+///
+/// Ordered most- to least-specific: a single-byte encoding accepts nearly
+/// any byte value, so it ties with a correctly-matching multi-byte encoding
+/// at zero replacement characters; listing the multi-byte candidates first
+/// lets [`Iterator::min_by_key`]'s first-wins tie-break prefer the more
+/// specific match. [`WINDOWS_1252`] goes last as the broadest catch-all.
+const SNIFF_CANDIDATES: &[&Encoding] = &[
+    SHIFT_JIS,
+    GB18030,
+    EUC_JP,
+    EUC_KR,
+    BIG5,
+    WINDOWS_1251,
+    ISO_8859_2,
+    KOI8_R,
+    WINDOWS_1252,
+];
+
+/// Number of leading bytes [`sniff_encoding`] inspects.
+const SNIFF_WINDOW: usize = 4096;
+
+/// Heuristically sniffs the encoding of a document that has neither a BOM
+/// nor a declared `encoding=` attribute, by inspecting up to
+/// [`SNIFF_WINDOW`] leading bytes of `bytes`.
+///
+/// First tries strict UTF-8 validation, since a valid multi-byte sequence is
+/// strong evidence the whole document is UTF-8. Failing that, scores
+/// [`SNIFF_CANDIDATES`] by how many U+FFFD replacement characters each
+/// produces over the window, and returns whichever produced the fewest.
+/// Returns `None` only if `bytes` is empty.
+pub fn sniff_encoding(bytes: &[u8]) -> Option<SniffedEncoding> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let window = &bytes[..bytes.len().min(SNIFF_WINDOW)];
+
+    if let Ok(text) = std::str::from_utf8(window) {
+        if !text.is_ascii() {
+            return Some(SniffedEncoding::Confident(UTF_8));
+        }
+    }
+
+    SNIFF_CANDIDATES
+        .iter()
+        .map(|&encoding| {
+            let (text, _, _) = encoding.decode(window);
+            let replacements = text.chars().filter(|&c| c == '\u{FFFD}').count();
+            (encoding, replacements)
+        })
+        .min_by_key(|&(_, replacements)| replacements)
+        .map(|(encoding, _)| SniffedEncoding::Guessed(encoding))
+}
+
+/// Shift state tracked while tokenizing `ISO-2022-JP`.
+///
+/// Plain ASCII is the only state in which `<`, `>`, `&` and `"` are markup;
+/// every other designation re-purposes those byte values as (half of) a
+/// payload character, introduced by an `ESC` escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShiftState {
+    /// ASCII, entered by `ESC ( B` (also the initial state).
+    Ascii,
+    /// JIS X 0201-1976 Roman, entered by `ESC ( J`.
+    JisX0201Roman,
+    /// JIS X 0208 (1978 or 1983), entered by `ESC $ @` / `ESC $ B`.
+    JisX0208,
+    /// JIS X 0201-1976 Kana, entered by `ESC ( I`.
+    JisX0201Kana,
+}
+
+impl ShiftState {
+    /// `true` while `<`, `>`, `&` and `"` still mean what they mean in ASCII.
+    ///
+    /// `JisX0201Roman` is included alongside `Ascii`: it differs from ASCII
+    /// only in a couple of code points outside the structural set (`\`/`~`),
+    /// so `<`, `>`, `&` and `"` are identical to their ASCII byte values.
+    fn is_structural(self) -> bool {
+        matches!(self, ShiftState::Ascii | ShiftState::JisX0201Roman)
+    }
+}
+
+/// How a [`Decoder`] should handle a malformed byte sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    /// Substitute U+FFFD for malformed sequences; decoding never fails.
+    /// This is the default.
+    #[default]
+    Replace,
+    /// Return an [`EncodingError`] carrying the byte offset of the first
+    /// malformed sequence and the encoding that rejected it.
+    Fail,
+}
+
+/// Decodes raw bytes according to the document's current encoding.
+///
+/// A `Reader` keeps one `Decoder` alive for the whole document and updates
+/// its encoding as a BOM, an `encoding=` declaration attribute, or a
+/// configured override is discovered. For `ISO-2022-JP` it additionally
+/// tracks the [`ShiftState`] introduced by `ESC` sequences seen so far, so
+/// that `Reader`'s structural byte scan can tell markup from payload.
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    pub(crate) encoding: &'static Encoding,
+    shift_state: Cell<ShiftState>,
+    mode: DecodeMode,
+}
+
+impl Decoder {
+    /// Creates a decoder for `encoding`, starting in the ASCII shift state.
+    pub(crate) fn new(encoding: &'static Encoding, mode: DecodeMode) -> Self {
+        Decoder {
+            encoding,
+            shift_state: Cell::new(ShiftState::Ascii),
+            mode,
+        }
+    }
+
+    /// Creates a decoder fixed to UTF-8 with the default (`Replace`) mode.
+    pub(crate) fn utf8() -> Self {
+        Decoder::new(UTF_8, DecodeMode::default())
+    }
+
+    /// The encoding this decoder currently interprets bytes as.
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Feeds `self.encoding()`'s shift state forward by one position.
+    ///
+    /// `remaining` is the yet-unconsumed input starting at the byte about to
+    /// be classified. A no-op for every encoding except `ISO-2022-JP`; called
+    /// by the tokenizer once per candidate structural byte before that byte
+    /// is tested, so an `ESC` sequence always updates the state before the
+    /// byte it introduces is looked at.
+    pub(crate) fn advance_shift_state(&self, remaining: &[u8]) {
+        if self.encoding != ISO_2022_JP {
+            return;
+        }
+        if remaining.first() != Some(&0x1B) || remaining.len() < 3 {
+            return;
+        }
+        let new_state = match &remaining[1..3] {
+            b"$@" | b"$B" => ShiftState::JisX0208,
+            b"(J" => ShiftState::JisX0201Roman,
+            b"(B" => ShiftState::Ascii,
+            b"(I" => ShiftState::JisX0201Kana,
+            _ => return,
+        };
+        self.shift_state.set(new_state);
+    }
+
+    /// Whether the byte at the position last passed to
+    /// [`advance_shift_state`](Decoder::advance_shift_state) should still be
+    /// treated as structural markup rather than encoded payload.
+    pub(crate) fn is_structural_context(&self) -> bool {
+        self.shift_state.get().is_structural()
+    }
+
+    /// Decodes `bytes` as this decoder's encoding, following the configured
+    /// [`DecodeMode`]: substituting U+FFFD for malformed sequences in
+    /// `Replace` mode, or failing with the offset of the first one in `Fail`
+    /// mode.
+    pub fn decode<'b>(&self, bytes: Cow<'b, [u8]>) -> Result<Cow<'b, str>, EncodingError> {
+        match self.mode {
+            DecodeMode::Replace => match bytes {
+                Cow::Borrowed(b) => Ok(self.encoding.decode_without_bom_handling(b).0),
+                Cow::Owned(b) => Ok(Cow::Owned(
+                    self.encoding.decode_without_bom_handling(&b).0.into_owned(),
+                )),
+            },
+            DecodeMode::Fail => {
+                let raw: &[u8] = &bytes;
+                let mut decoder = self.encoding.new_decoder_without_bom_handling();
+                let mut out = String::with_capacity(raw.len());
+                let mut total_read = 0;
+                loop {
+                    let (result, read) =
+                        decoder.decode_to_string_without_replacement(&raw[total_read..], &mut out, true);
+                    total_read += read;
+                    match result {
+                        DecoderResult::InputEmpty => return Ok(Cow::Owned(out)),
+                        DecoderResult::OutputFull => {
+                            out.reserve(raw.len() - total_read + 1);
+                        }
+                        DecoderResult::Malformed(bad_len, extra_len) => {
+                            let offset = total_read - bad_len as usize - extra_len as usize;
+                            return Err(EncodingError::new(self.encoding, offset));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl PartialEq for Decoder {
+    fn eq(&self, other: &Self) -> bool {
+        self.encoding == other.encoding
+    }
+}
+
+impl Eq for Decoder {}
+
+/// A byte sequence could not be decoded as the reported encoding, in
+/// [`DecodeMode::Fail`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingError {
+    encoding: &'static Encoding,
+    offset: usize,
+}
+
+impl EncodingError {
+    fn new(encoding: &'static Encoding, offset: usize) -> Self {
+        EncodingError { encoding, offset }
+    }
+
+    /// The encoding that rejected the input.
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// The byte offset, relative to the start of the decoded slice, of the
+    /// first malformed sequence.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "malformed byte sequence for encoding {} at byte offset {}",
+            self.encoding.name(),
+            self.offset
+        )
+    }
+}
+
+impl std::error::Error for EncodingError {}
@@ -0,0 +1,57 @@
+//! Error types returned by this crate.
+
+use std::fmt;
+use std::io;
+use std::str::Utf8Error;
+
+use crate::encoding::EncodingError;
+
+/// The error type used by this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// IO error occurred while reading or writing.
+    Io(io::Error),
+    /// Input decoded into UTF-8 is not well-formed.
+    Utf8(Utf8Error),
+    /// A byte sequence could not be decoded with the currently selected encoding.
+    Encoding(EncodingError),
+    /// Unexpected end of file.
+    UnexpectedEof(String),
+    /// The document is not well-formed.
+    Syntax(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Utf8(e) => write!(f, "UTF-8 error: {}", e),
+            Error::Encoding(e) => write!(f, "encoding error: {}", e),
+            Error::UnexpectedEof(e) => write!(f, "unexpected EOF during {}", e),
+            Error::Syntax(e) => write!(f, "syntax error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(e: Utf8Error) -> Self {
+        Error::Utf8(e)
+    }
+}
+
+impl From<EncodingError> for Error {
+    fn from(e: EncodingError) -> Self {
+        Error::Encoding(e)
+    }
+}
+
+/// A specialized [`Result`](std::result::Result) type for this crate's operations.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,174 @@
+//! XML events emitted by [`Reader`](crate::reader::Reader) and consumed by
+//! [`Writer`](crate::writer::Writer).
+
+use std::borrow::Cow;
+
+use crate::encoding::{Decoder, EncodingError};
+
+/// Start tag data: `<name attr="value">`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytesStart<'a> {
+    /// The whole content between `<` and `>`, excluding both.
+    buf: Cow<'a, [u8]>,
+    /// Length in bytes of the name at the start of `buf`.
+    name_len: usize,
+}
+
+impl<'a> BytesStart<'a> {
+    /// Creates a start tag from the raw content between `<` and `>` plus the
+    /// length of the leading element name.
+    ///
+    /// Takes `Into<Cow<str>>`, like [`BytesText::new`], so a literal such as
+    /// `BytesStart::from_content("paired attr=\"value\"", 6)` works directly;
+    /// element and attribute names are XML name characters, always valid
+    /// UTF-8. [`from_content_bytes`](BytesStart::from_content_bytes) is the
+    /// raw-bytes counterpart the tokenizer uses internally, since a non-UTF-8
+    /// source document's tag content isn't guaranteed to be valid UTF-8.
+    pub fn from_content<C: Into<Cow<'a, str>>>(content: C, name_len: usize) -> Self {
+        BytesStart {
+            buf: str_cow_into_bytes(content.into()),
+            name_len,
+        }
+    }
+
+    /// Creates a start tag from the raw, not-necessarily-UTF-8 bytes between
+    /// `<` and `>`, as produced by the tokenizer while scanning a document in
+    /// its source encoding.
+    pub(crate) fn from_content_bytes<C: Into<Cow<'a, [u8]>>>(content: C, name_len: usize) -> Self {
+        BytesStart {
+            buf: content.into(),
+            name_len,
+        }
+    }
+
+    /// The element name, including any namespace prefix.
+    pub fn name(&self) -> &[u8] {
+        &self.buf[..self.name_len]
+    }
+}
+
+/// Converts a `Cow<str>` to the equivalent `Cow<[u8]>` without copying the
+/// borrowed case.
+fn str_cow_into_bytes(s: Cow<str>) -> Cow<[u8]> {
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+/// End tag data: `</name>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytesEnd<'a> {
+    name: Cow<'a, [u8]>,
+}
+
+impl<'a> BytesEnd<'a> {
+    /// Creates an end tag from an element name.
+    ///
+    /// Takes `Into<Cow<str>>`; see
+    /// [`BytesStart::from_content`](BytesStart::from_content) for why.
+    /// [`new_bytes`](BytesEnd::new_bytes) is the raw-bytes counterpart the
+    /// tokenizer uses internally.
+    pub fn new<C: Into<Cow<'a, str>>>(name: C) -> Self {
+        BytesEnd {
+            name: str_cow_into_bytes(name.into()),
+        }
+    }
+
+    /// Creates an end tag from a raw, not-necessarily-UTF-8 element name, as
+    /// produced by the tokenizer while scanning a document in its source
+    /// encoding.
+    pub(crate) fn new_bytes<C: Into<Cow<'a, [u8]>>>(name: C) -> Self {
+        BytesEnd { name: name.into() }
+    }
+
+    /// The element name.
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+}
+
+/// Character data outside of tags, as raw bytes: XML entities are not
+/// unescaped and the bytes are not yet decoded from the document's encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytesText<'a> {
+    content: Cow<'a, [u8]>,
+    decoder: Decoder,
+}
+
+impl<'a> BytesText<'a> {
+    /// Creates text content from an already-decoded `&str`, tagged as UTF-8.
+    pub fn new(content: &'a str) -> Self {
+        BytesText {
+            content: Cow::Borrowed(content.as_bytes()),
+            decoder: Decoder::utf8(),
+        }
+    }
+
+    /// Creates text content from raw bytes plus the decoder that should be
+    /// used to interpret them.
+    pub fn wrap<C: Into<Cow<'a, [u8]>>>(content: C, decoder: Decoder) -> Self {
+        BytesText {
+            content: content.into(),
+            decoder,
+        }
+    }
+
+    /// Decodes this text according to the encoding reported by the reader
+    /// that produced it and the reader's configured [`DecodeMode`].
+    pub fn decode(&self) -> Result<Cow<'a, str>, EncodingError> {
+        self.decoder.decode(self.content.clone())
+    }
+}
+
+/// CDATA section content: `<![CDATA[ ... ]]>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytesCData<'a> {
+    content: Cow<'a, [u8]>,
+    decoder: Decoder,
+}
+
+impl<'a> BytesCData<'a> {
+    /// Creates a CDATA section from raw bytes plus the decoder that should be
+    /// used to interpret them.
+    pub fn wrap<C: Into<Cow<'a, [u8]>>>(content: C, decoder: Decoder) -> Self {
+        BytesCData {
+            content: content.into(),
+            decoder,
+        }
+    }
+
+    /// Decodes this section according to the encoding reported by the reader
+    /// that produced it and the reader's configured [`DecodeMode`].
+    pub fn decode(&self) -> Result<Cow<'a, str>, EncodingError> {
+        self.decoder.decode(self.content.clone())
+    }
+}
+
+/// A processing instruction: `<?target instructions?>`.
+pub type BytesPI<'a> = BytesText<'a>;
+
+/// The kind of item produced by [`Reader::read_event`](crate::reader::Reader::read_event).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// Start tag of an element with children: `<name attr="value">`.
+    Start(BytesStart<'a>),
+    /// End tag of an element: `</name>`.
+    End(BytesEnd<'a>),
+    /// Self-closing element tag: `<name attr="value"/>`.
+    Empty(BytesStart<'a>),
+    /// Character data between tags.
+    Text(BytesText<'a>),
+    /// Comment: `<!-- ... -->`.
+    Comment(BytesText<'a>),
+    /// CDATA section: `<![CDATA[ ... ]]>`.
+    CData(BytesCData<'a>),
+    /// XML declaration: `<?xml ... ?>`.
+    Decl(BytesText<'a>),
+    /// Processing instruction: `<?target instructions?>`.
+    PI(BytesPI<'a>),
+    /// Document type declaration: `<!DOCTYPE ...>`.
+    DocType(BytesText<'a>),
+    /// End of the input.
+    Eof,
+}
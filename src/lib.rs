@@ -0,0 +1,26 @@
+//! quick-xml is a high-performance XML pull-parser.
+//!
+//! ## Reading
+//!
+//! [`Reader`] is the entry point for reading XML. It operates on either an
+//! in-memory `&str` / `&[u8]` or anything implementing [`std::io::BufRead`],
+//! and yields a stream of [`Event`]s.
+//!
+//! ## Writing
+//!
+//! [`Writer`] mirrors the reader and serializes [`Event`]s back to bytes.
+//!
+//! [`Reader`]: reader::Reader
+//! [`Writer`]: writer::Writer
+//! [`Event`]: events::Event
+
+pub mod encoding;
+pub mod errors;
+pub mod events;
+pub mod reader;
+pub mod writer;
+
+pub use errors::{Error, Result};
+pub use events::Event;
+pub use reader::Reader;
+pub use writer::Writer;
@@ -0,0 +1,64 @@
+//! Reader configuration.
+
+use encoding_rs::Encoding;
+
+use crate::encoding::DecodeMode;
+
+/// Configuration for [`Reader`](super::Reader), obtained through
+/// [`Reader::config_mut`](super::Reader::config_mut).
+#[derive(Clone, Debug, Default)]
+pub struct ReaderConfig {
+    pub(super) trim_text: bool,
+    pub(super) override_encoding: Option<&'static Encoding>,
+    pub(super) default_encoding: Option<&'static Encoding>,
+    pub(super) decode_errors: DecodeMode,
+    pub(super) sniff_encoding: bool,
+}
+
+impl ReaderConfig {
+    /// If `true`, leading and trailing whitespace is trimmed from
+    /// [`Text`](crate::events::Event::Text) events. Defaults to `false`.
+    pub fn trim_text(&mut self, trim: bool) -> &mut Self {
+        self.trim_text = trim;
+        self
+    }
+
+    /// Forces every document read by this reader to be interpreted as
+    /// `encoding`, regardless of any BOM or `encoding=` declaration found in
+    /// it. A later declaration is still parsed but becomes a no-op.
+    ///
+    /// Has no effect on a [`Reader::from_str`](super::Reader::from_str)
+    /// reader, which is always UTF-8.
+    pub fn override_encoding(&mut self, encoding: &'static Encoding) -> &mut Self {
+        self.override_encoding = Some(encoding);
+        self
+    }
+
+    /// Sets the encoding assumed for a document that has neither a BOM nor
+    /// an `encoding=` declaration. Ignored once either is present, and
+    /// itself ignored if [`override_encoding`](ReaderConfig::override_encoding)
+    /// is set.
+    pub fn default_encoding(&mut self, encoding: &'static Encoding) -> &mut Self {
+        self.default_encoding = Some(encoding);
+        self
+    }
+
+    /// Controls how [`BytesText::decode`](crate::events::BytesText::decode)
+    /// and friends handle a byte sequence that is malformed for the
+    /// document's encoding. Defaults to [`DecodeMode::Replace`].
+    pub fn decode_errors(&mut self, mode: DecodeMode) -> &mut Self {
+        self.decode_errors = mode;
+        self
+    }
+
+    /// If `true`, a document with neither a BOM nor a declared `encoding=`
+    /// falls back to [`sniff_encoding`](crate::encoding::sniff_encoding)'s
+    /// content-based guess instead of UTF-8. Ignored if
+    /// [`default_encoding`](ReaderConfig::default_encoding) is also set, since
+    /// an explicit fallback always wins over a heuristic one. Defaults to
+    /// `false`.
+    pub fn sniff_encoding(&mut self, sniff: bool) -> &mut Self {
+        self.sniff_encoding = sniff;
+        self
+    }
+}
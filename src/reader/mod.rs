@@ -0,0 +1,407 @@
+//! A pull parser for XML, modeled after `xmlPullParserAPI`.
+
+mod config;
+
+pub use config::ReaderConfig;
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::encoding::{detect_encoding, sniff_encoding, Decoder};
+use crate::errors::{Error, Result};
+use crate::events::{BytesCData, BytesEnd, BytesStart, BytesText, Event};
+
+/// A low-level, pull-based XML parser.
+///
+/// `Reader` reads the whole input eagerly but tokenizes it lazily, one
+/// [`Event`] per [`read_event_into`](Reader::read_event_into) call. It tracks
+/// the document's encoding (from a BOM, an `encoding=` declaration, or a
+/// configured override) and exposes the [`Decoder`] for the encoding
+/// currently in effect through [`decoder`](Reader::decoder). Each event
+/// payload carries a clone of the decoder that was in effect when it was
+/// read, so it decodes correctly even after the encoding has changed.
+pub struct Reader {
+    source: Vec<u8>,
+    position: usize,
+    decoder: Decoder,
+    config: ReaderConfig,
+    /// `true` for `Reader::from_str`: the input is already `&str`, so nothing
+    /// is ever allowed to change the decoder away from UTF-8.
+    encoding_locked: bool,
+    /// Whether the leading BOM (if any) has already been consumed.
+    bom_checked: bool,
+    /// Whether an XML declaration has already had a chance to set the
+    /// encoding; per the spec there is only ever one, but malformed input
+    /// may contain more, and only the first counts.
+    declaration_seen: bool,
+    /// Scratch buffer reused by [`read_event`](Reader::read_event) so it can
+    /// hand back a borrowed [`Event`] without requiring the caller to supply
+    /// one, the way [`read_event_into`](Reader::read_event_into) does.
+    scratch: Vec<u8>,
+}
+
+impl Reader {
+    /// Creates a reader over an in-memory, already-decoded string. The
+    /// encoding is permanently UTF-8; a declared `encoding=` attribute is
+    /// parsed but never applied.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        Reader {
+            source: s.as_bytes().to_vec(),
+            position: 0,
+            decoder: Decoder::utf8(),
+            config: ReaderConfig::default(),
+            encoding_locked: true,
+            bom_checked: false,
+            declaration_seen: false,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Creates a reader over a byte source, whose encoding is determined from
+    /// a BOM and/or an `encoding=` declaration as parsing proceeds.
+    pub fn from_reader<R: Read>(mut reader: R) -> Self {
+        let mut source = Vec::new();
+        reader
+            .read_to_end(&mut source)
+            .expect("reading from the provided source failed");
+        Reader {
+            source,
+            position: 0,
+            decoder: Decoder::utf8(),
+            config: ReaderConfig::default(),
+            encoding_locked: false,
+            bom_checked: false,
+            declaration_seen: false,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Mutable access to this reader's configuration.
+    pub fn config_mut(&mut self) -> &mut ReaderConfig {
+        &mut self.config
+    }
+
+    /// The decoder for the encoding currently in effect.
+    pub fn decoder(&self) -> Decoder {
+        self.decoder.clone()
+    }
+
+    /// Reads the next [`Event`], copying any borrowed payload into `buf`.
+    pub fn read_event_into<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        self.resolve_initial_encoding();
+
+        if self.position >= self.source.len() {
+            return Ok(Event::Eof);
+        }
+
+        if self.source[self.position] == b'<' {
+            self.read_markup(buf)
+        } else {
+            self.read_text(buf)
+        }
+    }
+
+    /// Reads the next [`Event`], borrowing its payload from an internal
+    /// buffer reused across calls instead of one supplied by the caller.
+    pub fn read_event(&mut self) -> Result<Event<'_>> {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        let result = self.read_event_into(&mut scratch);
+        // SAFETY: erasing the lifetime here only lets us move `scratch` back
+        // into `self` on the next line; it doesn't change the bytes the
+        // event borrows. Moving `scratch` relocates just the `Vec` header,
+        // not its heap allocation, so those bytes stay valid for as long as
+        // `self.scratch` isn't touched again - which the borrow checker
+        // guarantees, since doing so requires `&mut self` and this event
+        // borrows `self` for its entire lifetime.
+        let result: Result<Event<'static>> = unsafe { std::mem::transmute(result) };
+        self.scratch = scratch;
+        result
+    }
+
+    /// Consumes the leading BOM (if any) and resolves the encoding it, a
+    /// configured override, or a configured default implies, unless this
+    /// reader is locked to UTF-8.
+    fn resolve_initial_encoding(&mut self) {
+        if self.bom_checked {
+            return;
+        }
+        self.bom_checked = true;
+        let (detected, bom_len) =
+            detect_encoding(&self.source[self.position..]).unwrap_or((UTF_8, 0));
+        self.position += bom_len;
+
+        if self.encoding_locked {
+            return;
+        }
+        let encoding = match self.config.override_encoding {
+            Some(encoding) => encoding,
+            None if bom_len == 0 => self
+                .config
+                .default_encoding
+                .or_else(|| {
+                    if self.config.sniff_encoding {
+                        sniff_encoding(&self.source[self.position..]).map(|s| s.encoding())
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(detected),
+            None => detected,
+        };
+        self.decoder = Decoder::new(encoding, self.config.decode_errors);
+    }
+
+    fn read_text<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let start = self.position;
+        let end = self.find_structural(b'<', start).unwrap_or(self.source.len());
+        let mut content = &self.source[start..end];
+        self.position = end;
+
+        if self.config.trim_text {
+            content = trim_ascii_whitespace(content);
+        }
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        Ok(Event::Text(BytesText::wrap(
+            Cow::Borrowed(&buf[..]),
+            self.decoder.clone(),
+        )))
+    }
+
+    fn read_markup<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let rest = &self.source[self.position + 1..];
+        if rest.starts_with(b"?xml") && rest.get(4).is_none_or(|&b| !is_name_byte(b)) {
+            self.read_decl(buf)
+        } else if rest.starts_with(b"?") {
+            self.read_pi(buf)
+        } else if rest.starts_with(b"!--") {
+            self.read_comment(buf)
+        } else if rest.starts_with(b"![CDATA[") {
+            self.read_cdata(buf)
+        } else if rest.starts_with(b"!DOCTYPE") || rest.starts_with(b"!doctype") {
+            self.read_doctype(buf)
+        } else if rest.starts_with(b"/") {
+            self.read_end(buf)
+        } else {
+            self.read_start(buf)
+        }
+    }
+
+    fn read_decl<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let content_start = self.position + 2; // past "<?"
+        let close = self
+            .find_structural_str(b"?>", content_start)
+            .ok_or_else(|| Error::UnexpectedEof("XML declaration".into()))?;
+        let content = &self.source[content_start..close];
+
+        if !self.declaration_seen {
+            self.declaration_seen = true;
+            // An override wins over whatever the document itself declares.
+            if !self.encoding_locked && self.config.override_encoding.is_none() {
+                if let Some(encoding) = extract_declared_encoding(content) {
+                    self.decoder = Decoder::new(encoding, self.config.decode_errors);
+                }
+            }
+        }
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        self.position = close + 2;
+        Ok(Event::Decl(BytesText::wrap(
+            Cow::Borrowed(&buf[..]),
+            self.decoder.clone(),
+        )))
+    }
+
+    fn read_pi<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let content_start = self.position + 2; // past "<?"
+        let close = self
+            .find_structural_str(b"?>", content_start)
+            .ok_or_else(|| Error::UnexpectedEof("processing instruction".into()))?;
+        let content = &self.source[content_start..close];
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        self.position = close + 2;
+        Ok(Event::PI(BytesText::wrap(
+            Cow::Borrowed(&buf[..]),
+            self.decoder.clone(),
+        )))
+    }
+
+    fn read_comment<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let content_start = self.position + 4; // past "<!--"
+        let close = self
+            .find_structural_str(b"-->", content_start)
+            .ok_or_else(|| Error::UnexpectedEof("comment".into()))?;
+        let content = &self.source[content_start..close];
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        self.position = close + 3;
+        Ok(Event::Comment(BytesText::wrap(
+            Cow::Borrowed(&buf[..]),
+            self.decoder.clone(),
+        )))
+    }
+
+    fn read_cdata<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let content_start = self.position + 9; // past "<![CDATA["
+        let close = self
+            .find_structural_str(b"]]>", content_start)
+            .ok_or_else(|| Error::UnexpectedEof("CDATA section".into()))?;
+        let content = &self.source[content_start..close];
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        self.position = close + 3;
+        Ok(Event::CData(BytesCData::wrap(
+            Cow::Borrowed(&buf[..]),
+            self.decoder.clone(),
+        )))
+    }
+
+    fn read_doctype<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let content_start = self.position + 2; // past "<!"
+        let close = self
+            .find_structural(b'>', content_start)
+            .ok_or_else(|| Error::UnexpectedEof("DOCTYPE declaration".into()))?;
+        let content = &self.source[content_start..close];
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        self.position = close + 1;
+        Ok(Event::DocType(BytesText::wrap(
+            Cow::Borrowed(&buf[..]),
+            self.decoder.clone(),
+        )))
+    }
+
+    fn read_end<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let name_start = self.position + 2; // past "</"
+        let close = self
+            .find_structural(b'>', name_start)
+            .ok_or_else(|| Error::UnexpectedEof("end tag".into()))?;
+        let name = &self.source[name_start..close];
+
+        buf.clear();
+        buf.extend_from_slice(name);
+        self.position = close + 1;
+        Ok(Event::End(BytesEnd::new_bytes(Cow::Borrowed(&buf[..]))))
+    }
+
+    fn read_start<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>> {
+        let content_start = self.position + 1; // past "<"
+        let close = self
+            .find_tag_close(content_start)
+            .ok_or_else(|| Error::UnexpectedEof("start tag".into()))?;
+        let mut content = &self.source[content_start..close];
+        self.position = close + 1;
+
+        let empty = content.ends_with(b"/");
+        if empty {
+            content = &content[..content.len() - 1];
+        }
+        let name_len = content
+            .iter()
+            .position(|&b| !is_name_byte(b))
+            .unwrap_or(content.len());
+
+        buf.clear();
+        buf.extend_from_slice(content);
+        let start = BytesStart::from_content_bytes(Cow::Borrowed(&buf[..]), name_len);
+        Ok(if empty {
+            Event::Empty(start)
+        } else {
+            Event::Start(start)
+        })
+    }
+
+    /// Scans forward from `from` for the next occurrence of `byte` that is
+    /// not shadowed by a non-ASCII `ISO-2022-JP` shift state, advancing the
+    /// decoder's shift state one position at a time as it goes.
+    fn find_structural(&self, byte: u8, from: usize) -> Option<usize> {
+        let mut i = from;
+        while i < self.source.len() {
+            self.decoder.advance_shift_state(&self.source[i..]);
+            if self.source[i] == byte && self.decoder.is_structural_context() {
+                return Some(i);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Like [`find_structural`](Reader::find_structural), but for a
+    /// multi-byte marker such as `"-->"`.
+    fn find_structural_str(&self, needle: &[u8], from: usize) -> Option<usize> {
+        let mut i = from;
+        while i + needle.len() <= self.source.len() {
+            self.decoder.advance_shift_state(&self.source[i..]);
+            if self.decoder.is_structural_context() && self.source[i..].starts_with(needle) {
+                return Some(i);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Finds the `>` that closes a start/empty tag, skipping over `>` bytes
+    /// that appear inside a quoted attribute value.
+    fn find_tag_close(&self, from: usize) -> Option<usize> {
+        let mut i = from;
+        let mut quote: Option<u8> = None;
+        while i < self.source.len() {
+            self.decoder.advance_shift_state(&self.source[i..]);
+            let byte = self.source[i];
+            if self.decoder.is_structural_context() {
+                match quote {
+                    Some(q) if byte == q => quote = None,
+                    None if byte == b'"' || byte == b'\'' => quote = Some(byte),
+                    None if byte == b'>' => return Some(i),
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+/// Extracts the value of an `encoding="..."` (or `'...'`) attribute from the
+/// raw content of an XML declaration, if present and resolvable.
+fn extract_declared_encoding(decl: &[u8]) -> Option<&'static Encoding> {
+    let text = std::str::from_utf8(decl).ok()?;
+    let after_key = &text[text.find("encoding")? + "encoding".len()..];
+    let after_eq = after_key[after_key.find('=')? + 1..].trim_start();
+    let quote = after_eq.chars().next()?;
+    if quote != '\'' && quote != '"' {
+        return None;
+    }
+    let rest = &after_eq[quote.len_utf8()..];
+    let label = &rest[..rest.find(quote)?];
+    Encoding::for_label(label.as_bytes())
+}
+
+/// Whether `b` can appear in an XML element or attribute name (ASCII subset).
+fn is_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':')
+}
+
+/// Trims leading and trailing ASCII whitespace, the same subset XML treats
+/// as insignificant (space, tab, CR, LF).
+fn trim_ascii_whitespace(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
@@ -0,0 +1,230 @@
+//! Serializing [`Event`]s back to bytes, optionally into an encoding other
+//! than UTF-8.
+
+use std::io::{self, Write as IoWrite};
+
+use encoding_rs::{Encoder, Encoding, EncoderResult, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::events::{BytesCData, BytesText, Event};
+
+/// Serializes [`Event`]s to an underlying writer.
+///
+/// By default a `Writer` emits UTF-8. [`with_encoding`](Writer::with_encoding)
+/// instead transcodes every event's text content to a target encoding as it
+/// is written, writing a matching `<?xml ... encoding="..."?>` declaration
+/// (and, for UTF-16, the matching BOM) before the first event — unless that
+/// first event is itself a `Decl`, in which case the caller's own
+/// declaration is written instead and the auto one is suppressed. Characters
+/// the target encoding cannot represent are emitted as numeric character
+/// references (`&#xNNNN;`) rather than causing a write failure, the same way
+/// browsers serialize.
+pub struct Writer<W> {
+    inner: W,
+    encoding: &'static Encoding,
+    header_written: bool,
+    /// `Some` for every non-UTF-8 target, held for the writer's whole
+    /// lifetime so a stateful encoding such as `ISO-2022-JP` keeps its shift
+    /// state across events instead of resetting to ASCII on every call.
+    encoder: Option<Encoder>,
+}
+
+impl<W: IoWrite> Writer<W> {
+    /// Creates a writer that serializes to UTF-8.
+    pub fn new(inner: W) -> Self {
+        Writer {
+            inner,
+            encoding: UTF_8,
+            header_written: false,
+            encoder: None,
+        }
+    }
+
+    /// Creates a writer that transcodes every event's text content to
+    /// `encoding` as it is written.
+    pub fn with_encoding(inner: W, encoding: &'static Encoding) -> Self {
+        Writer {
+            inner,
+            encoding,
+            header_written: false,
+            encoder: Some(encoding.new_encoder()),
+        }
+    }
+
+    /// Consumes this writer, flushing the target encoding's trailing state
+    /// (e.g. the `ESC ( B` needed to leave `ISO-2022-JP`'s non-ASCII shift
+    /// states) and returning the underlying one.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        if let Some(mut encoder) = self.encoder.take() {
+            let mut out = [0u8; 1024];
+            loop {
+                let (result, _, written) =
+                    encoder.encode_from_utf8_without_replacement("", &mut out, true);
+                self.inner.write_all(&out[..written])?;
+                if result == EncoderResult::InputEmpty {
+                    break;
+                }
+            }
+        }
+        Ok(self.inner)
+    }
+
+    /// Writes a single event.
+    pub fn write_event(&mut self, event: Event<'_>) -> io::Result<()> {
+        // A caller-supplied `Decl` owns the declaration: replaying a
+        // document's original `Decl` event after the auto header would
+        // double it up, so suppress the auto declaration (but still write
+        // the BOM, which `Decl` content can't carry) when one is coming.
+        self.write_header_if_needed(matches!(event, Event::Decl(_)))?;
+        match event {
+            Event::Start(e) => {
+                self.write_str("<")?;
+                self.write_name(e.name())?;
+                self.write_str(">")
+            }
+            Event::End(e) => {
+                self.write_str("</")?;
+                self.write_name(e.name())?;
+                self.write_str(">")
+            }
+            Event::Empty(e) => {
+                self.write_str("<")?;
+                self.write_name(e.name())?;
+                self.write_str("/>")
+            }
+            Event::Text(e) => self.write_text(&e),
+            Event::Comment(e) => {
+                self.write_str("<!--")?;
+                self.write_text(&e)?;
+                self.write_str("-->")
+            }
+            Event::CData(e) => {
+                self.write_str("<![CDATA[")?;
+                self.write_cdata(&e)?;
+                self.write_str("]]>")
+            }
+            Event::Decl(e) => {
+                // `Decl` content already starts with `xml` (the reader's
+                // `read_decl` slices from just past `<?`), same as `PI`'s
+                // content starts just past its own `<?`.
+                self.write_str("<?")?;
+                self.write_text(&e)?;
+                self.write_str("?>")
+            }
+            Event::PI(e) => {
+                self.write_str("<?")?;
+                self.write_text(&e)?;
+                self.write_str("?>")
+            }
+            Event::DocType(e) => {
+                self.write_str("<!")?;
+                self.write_text(&e)?;
+                self.write_str(">")
+            }
+            Event::Eof => Ok(()),
+        }
+    }
+
+    /// Writes the BOM (for UTF-16 targets) and the `encoding=` declaration
+    /// (for every non-UTF-8 target) ahead of the first event, mirroring the
+    /// BOM/declaration pairing `detect_encoding` recognizes on the read side.
+    ///
+    /// The BOM itself is written as raw bytes (it has no UTF-8
+    /// representation to transcode from); the declaration that follows it is
+    /// routed through [`write_str`](Writer::write_str) like any other
+    /// content so it actually ends up in the target encoding.
+    ///
+    /// `suppress_declaration` skips only the auto-generated declaration
+    /// text, not the BOM, for the caller that is about to write its own
+    /// `Decl` event.
+    fn write_header_if_needed(&mut self, suppress_declaration: bool) -> io::Result<()> {
+        if self.header_written || self.encoding == UTF_8 {
+            self.header_written = true;
+            return Ok(());
+        }
+        self.header_written = true;
+        if self.encoding == UTF_16LE {
+            self.inner.write_all(&[0xFF, 0xFE])?;
+        } else if self.encoding == UTF_16BE {
+            self.inner.write_all(&[0xFE, 0xFF])?;
+        }
+        if suppress_declaration {
+            return Ok(());
+        }
+        self.write_str(&format!(
+            "<?xml version=\"1.0\" encoding=\"{}\"?>",
+            self.encoding.name()
+        ))
+    }
+
+    fn write_text(&mut self, text: &BytesText<'_>) -> io::Result<()> {
+        match text.decode() {
+            Ok(s) => self.write_str(&s),
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    fn write_cdata(&mut self, text: &BytesCData<'_>) -> io::Result<()> {
+        match text.decode() {
+            Ok(s) => self.write_str(&s),
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Writes an element name through the target encoding like any other
+    /// content; an encoding target that isn't an ASCII superset, such as
+    /// UTF-16, needs this to avoid corrupting markup bytes. The name bytes
+    /// must be valid UTF-8: a name read from a non-UTF-8 document is kept in
+    /// its source encoding, and if those bytes are not valid UTF-8 the write
+    /// fails with [`io::ErrorKind::InvalidData`].
+    fn write_name(&mut self, name: &[u8]) -> io::Result<()> {
+        let s = std::str::from_utf8(name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.write_str(s)
+    }
+
+    /// Transcodes `s` to this writer's target encoding, substituting a
+    /// numeric character reference for anything the target can't represent.
+    ///
+    /// Reuses `self.encoder` across calls with `last = false`, so a
+    /// stateful encoding's shift state carries over between events; only
+    /// [`into_inner`](Writer::into_inner) passes `last = true` to flush the
+    /// final state.
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        if self.encoding == UTF_8 {
+            return self.inner.write_all(s.as_bytes());
+        }
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // `encoding_rs` encodes UTF-16 targets as UTF-8, so emit the
+            // code units directly.
+            let mut bytes = Vec::with_capacity(s.len() * 2);
+            for unit in s.encode_utf16() {
+                if self.encoding == UTF_16LE {
+                    bytes.extend_from_slice(&unit.to_le_bytes());
+                } else {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            return self.inner.write_all(&bytes);
+        }
+
+        let encoder = self
+            .encoder
+            .as_mut()
+            .expect("encoder is Some whenever encoding != UTF_8");
+        let mut remaining = s;
+        let mut out = [0u8; 1024];
+        loop {
+            let (result, read, written) =
+                encoder.encode_from_utf8_without_replacement(remaining, &mut out, false);
+            self.inner.write_all(&out[..written])?;
+            remaining = &remaining[read..];
+            match result {
+                EncoderResult::InputEmpty => return Ok(()),
+                EncoderResult::OutputFull => continue,
+                EncoderResult::Unmappable(ch) => {
+                    write!(self.inner, "&#x{:X};", ch as u32)?;
+                }
+            }
+        }
+    }
+}
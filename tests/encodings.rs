@@ -1,4 +1,4 @@
-use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251};
+use encoding_rs::{KOI8_R, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251};
 use pretty_assertions::assert_eq;
 use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event::*};
 use quick_xml::reader::Reader;
@@ -26,6 +26,37 @@ mod decode {
         assert_eq!(detect_encoding(UTF16BE_TEXT_WITH_BOM), Some((UTF_16BE, 2)));
         assert_eq!(detect_encoding(UTF16LE_TEXT_WITH_BOM), Some((UTF_16LE, 2)));
     }
+
+    mod sniff {
+        use super::*;
+        use encoding_rs::SHIFT_JIS;
+        use pretty_assertions::assert_eq;
+        use quick_xml::encoding::{sniff_encoding, SniffedEncoding};
+
+        #[test]
+        fn valid_multi_byte_utf8_is_confident() {
+            assert_eq!(
+                sniff_encoding("café".as_bytes()),
+                Some(SniffedEncoding::Confident(UTF_8))
+            );
+        }
+
+        #[test]
+        fn legacy_multi_byte_encoding_is_guessed() {
+            // Shift_JIS for "あ" (hiragana A), which is not a valid UTF-8
+            // sequence. Several candidates decode it without replacement
+            // characters; Shift_JIS wins as the first of them.
+            assert_eq!(
+                sniff_encoding(b"\x82\xA0"),
+                Some(SniffedEncoding::Guessed(SHIFT_JIS))
+            );
+        }
+
+        #[test]
+        fn empty_input_is_not_sniffed() {
+            assert_eq!(sniff_encoding(b""), None);
+        }
+    }
 }
 
 #[test]
@@ -173,9 +204,7 @@ mod detect {
     check_detection!(euc_kr, EUC_KR, "EUC-KR");
     check_detection!(gb18030, GB18030, "gb18030");
     check_detection!(gbk, GBK, "GBK");
-    // TODO: XML in this encoding cannot be parsed successfully until #158 resolves
-    // We only read the first event to ensure, that encoding detected correctly
-    detect_test!(iso_2022_jp, ISO_2022_JP, "ISO-2022-JP" break);
+    check_detection!(iso_2022_jp, ISO_2022_JP, "ISO-2022-JP");
     check_detection!(shift_jis, SHIFT_JIS, "Shift_JIS");
 
     // legacy single-byte encodings (19)
@@ -271,3 +300,117 @@ fn str_always_has_utf8() {
 
     assert_eq!(reader.read_event().unwrap(), Eof);
 }
+
+/// Checks that `override_encoding` wins over both a BOM and a later
+/// declaration
+#[test]
+fn override_encoding_ignores_bom_and_declaration() {
+    let mut reader =
+        Reader::from_reader(b"\xFF\xFE<?xml encoding='windows-1251'?>".as_ref());
+    reader.config_mut().override_encoding(KOI8_R);
+    let mut buf = Vec::new();
+
+    assert!(matches!(reader.read_event_into(&mut buf).unwrap(), Decl(_)));
+    assert_eq!(reader.decoder().encoding(), KOI8_R);
+
+    assert_eq!(reader.read_event_into(&mut buf).unwrap(), Eof);
+}
+
+/// Checks that `default_encoding` only applies when there is neither a BOM
+/// nor a declaration, and that a declaration still wins over it
+#[test]
+fn default_encoding_only_applies_without_bom_or_declaration() {
+    let mut reader = Reader::from_reader(b"plain text, no declaration".as_ref());
+    reader.config_mut().default_encoding(KOI8_R);
+
+    assert_eq!(reader.decoder().encoding(), UTF_8);
+    let mut buf = Vec::new();
+    reader.read_event_into(&mut buf).unwrap();
+    assert_eq!(reader.decoder().encoding(), KOI8_R);
+
+    let mut reader = Reader::from_reader(b"<?xml encoding='windows-1251'?>".as_ref());
+    reader.config_mut().default_encoding(KOI8_R);
+    let mut buf = Vec::new();
+    assert!(matches!(reader.read_event_into(&mut buf).unwrap(), Decl(_)));
+    assert_eq!(reader.decoder().encoding(), WINDOWS_1251);
+}
+
+/// Checks that `sniff_encoding(true)` is only consulted for a document with
+/// neither a BOM nor a declaration, and is ignored once `default_encoding`
+/// is also configured
+#[test]
+fn sniff_encoding_guesses_without_bom_or_declaration() {
+    use encoding_rs::SHIFT_JIS;
+
+    let mut reader = Reader::from_reader(b"\x82\xA0".as_ref());
+    reader.config_mut().sniff_encoding(true);
+
+    assert_eq!(reader.decoder().encoding(), UTF_8);
+    let mut buf = Vec::new();
+    reader.read_event_into(&mut buf).unwrap();
+    assert_eq!(reader.decoder().encoding(), SHIFT_JIS);
+
+    let mut reader = Reader::from_reader(b"\x82\xA0".as_ref());
+    reader.config_mut().sniff_encoding(true);
+    reader.config_mut().default_encoding(KOI8_R);
+    let mut buf = Vec::new();
+    reader.read_event_into(&mut buf).unwrap();
+    assert_eq!(reader.decoder().encoding(), KOI8_R);
+}
+
+/// Checks that `decode_errors(DecodeMode::Fail)` reports the offset of the
+/// first malformed byte instead of silently substituting U+FFFD
+#[test]
+fn decode_errors_fail_mode_reports_offset() {
+    use quick_xml::encoding::DecodeMode;
+
+    let mut reader = Reader::from_reader(b"<a>ab\xFFcd</a>".as_ref());
+    reader.config_mut().decode_errors(DecodeMode::Fail);
+    let mut buf = Vec::new();
+
+    assert!(matches!(reader.read_event_into(&mut buf).unwrap(), Start(_)));
+    let text = match reader.read_event_into(&mut buf).unwrap() {
+        Text(e) => e,
+        e => panic!("expected Text, got {:?}", e),
+    };
+    let err = text.decode().unwrap_err();
+    assert_eq!(err.offset(), 2);
+    assert_eq!(err.encoding(), UTF_8);
+}
+
+/// Checks that `Writer::with_encoding` writes a matching declaration and
+/// transcodes text content to the target encoding
+#[test]
+fn writer_transcodes_to_target_encoding() {
+    use quick_xml::writer::Writer;
+
+    let mut out = Vec::new();
+    let mut writer = Writer::with_encoding(&mut out, WINDOWS_1251);
+    writer
+        .write_event(Start(BytesStart::from_content("root", 4)))
+        .unwrap();
+    // Cyrillic "А" (U+0410) encodes to the single byte 0xC0 in windows-1251.
+    writer.write_event(Text(BytesText::new("\u{0410}"))).unwrap();
+    writer.write_event(End(BytesEnd::new("root"))).unwrap();
+
+    assert!(out.starts_with(
+        b"<?xml version=\"1.0\" encoding=\"windows-1251\"?><root>\xC0</root>"
+    ));
+}
+
+/// Checks that a character unrepresentable in the target encoding is written
+/// as a numeric character reference instead of failing the write
+#[test]
+fn writer_escapes_unmappable_characters() {
+    use quick_xml::writer::Writer;
+
+    let mut out = Vec::new();
+    let mut writer = Writer::with_encoding(&mut out, WINDOWS_1251);
+    // U+1F600 has no representation in windows-1251.
+    writer
+        .write_event(Text(BytesText::new("\u{1F600}")))
+        .unwrap();
+
+    let written = String::from_utf8(out).unwrap();
+    assert!(written.ends_with("&#x1F600;"));
+}